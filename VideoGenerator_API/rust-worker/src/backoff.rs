@@ -0,0 +1,66 @@
+//! Exponential backoff with jitter, shared by the XREAD-level reconnect path
+//! (`connect_with_backoff`) and in-process job retries (`run_python_with_retries`),
+//! so neither retry loop lands every worker on the same wall-clock tick.
+
+use std::time::Duration;
+
+/// `base * 2^attempt`, capped at `max`, with up to 50% additional jitter.
+/// `attempt` is 0-based (the first retry after an initial failure). The
+/// `max` clamp is applied after jitter (not before), so `max` is always a
+/// true ceiling on the returned delay — every caller passes `max` expecting
+/// it to bound how long they can block.
+pub fn next_delay(attempt: u32, base: Duration, max: Duration) -> Duration {
+    let factor = 1u32.checked_shl(attempt).unwrap_or(u32::MAX);
+    let exp = base.saturating_mul(factor);
+    exp.mul_f64(1.0 + jitter_fraction() * 0.5).min(max)
+}
+
+/// A cheap, adequate-for-backoff source of pseudo-randomness seeded from the
+/// current time and thread, mirroring `lock::random_token`'s choice to avoid
+/// pulling in a dedicated RNG dependency just to jitter a sleep.
+fn jitter_fraction() -> f64 {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let mut hasher = DefaultHasher::new();
+    (nanos, std::thread::current().id()).hash(&mut hasher);
+    (hasher.finish() % 1000) as f64 / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn never_exceeds_max_even_with_jitter_and_a_large_attempt() {
+        let max = Duration::from_secs(30);
+        for attempt in 0..40 {
+            let delay = next_delay(attempt, Duration::from_millis(250), max);
+            assert!(delay <= max, "attempt={attempt} delay={delay:?} exceeded max={max:?}");
+        }
+    }
+
+    #[test]
+    fn grows_with_attempt_before_hitting_the_max_clamp() {
+        let base = Duration::from_millis(100);
+        let max = Duration::from_secs(60);
+        // At jitter=0 the floor for each attempt is base * 2^attempt; even
+        // with up to 50% jitter on top, a high enough attempt's floor must
+        // exceed a low attempt's ceiling for growth to be observable here.
+        let low = next_delay(0, base, max);
+        let high = next_delay(4, base, max);
+        assert!(high > low, "low={low:?} high={high:?}");
+    }
+
+    #[test]
+    fn a_huge_attempt_does_not_overflow_and_still_clamps_to_max() {
+        let max = Duration::from_secs(5);
+        let delay = next_delay(u32::MAX, Duration::from_millis(250), max);
+        assert_eq!(delay, max);
+    }
+}
@@ -0,0 +1,79 @@
+//! Explicit policy for what happens when the worker-pool channel (see
+//! `workerpool`) saturates, instead of leaving "one slow job stalls
+//! everything" as an implicit side effect of a bounded channel.
+//!
+//! - `block` (default): the reader blocks on `tx.send`, pausing XREAD. Safe,
+//!   preserves at-least-once, identical to plain channel backpressure.
+//! - `drop_oldest`: sacrifices the oldest entry still sitting in the channel
+//!   (not yet claimed by a worker) to make room, recording it to a side
+//!   "skipped" marker for audit.
+//! - `shed`: temporarily stops handing new entries to the pool and leaves
+//!   them unread on the stream itself, relying on the existing MINID
+//!   retention window (`trim_stream_minid`) to buffer them in Redis.
+
+use anyhow::Result;
+use redis::AsyncCommands;
+use std::str::FromStr;
+
+/// Audit trail for entries `drop_oldest` sacrificed: one key per entry_id,
+/// holding its jid.
+const SKIPPED_KEY_NS: &str = "videogen:skipped";
+/// Keep skipped markers around only long enough to audit, same window as
+/// `checkpoint::COMPLETED_TTL_SECS` — one key per entry (rather than one
+/// shared hash) so each expires on its own instead of the audit trail
+/// growing without bound under sustained `drop_oldest` activation.
+const SKIPPED_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackpressurePolicy {
+    Block,
+    DropOldest,
+    Shed,
+}
+
+impl FromStr for BackpressurePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "block" => Ok(Self::Block),
+            "drop_oldest" => Ok(Self::DropOldest),
+            "shed" => Ok(Self::Shed),
+            other => Err(format!(
+                "unknown BACKPRESSURE_POLICY={other} (expected block|drop_oldest|shed)"
+            )),
+        }
+    }
+}
+
+impl BackpressurePolicy {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Block => "block",
+            Self::DropOldest => "drop_oldest",
+            Self::Shed => "shed",
+        }
+    }
+}
+
+/// Record an entry `drop_oldest` evicted from the channel unprocessed, so
+/// operators can audit what was sacrificed under sustained saturation.
+pub async fn record_skipped(
+    con: &mut redis::aio::MultiplexedConnection,
+    entry_id: &str,
+    jid: &str,
+) -> Result<()> {
+    let key = format!("{SKIPPED_KEY_NS}:{entry_id}");
+    con.set_ex::<_, _, ()>(&key, jid, SKIPPED_TTL_SECS).await?;
+    Ok(())
+}
+
+/// Emit a metric line the moment a policy actually kicks in (the channel
+/// was observed full), so operators can see sustained saturation as an
+/// explicit, observable event rather than inferring it from latency alone.
+pub fn log_activated(policy: BackpressurePolicy, entry_id: &str) {
+    eprintln!(
+        "[backpressure.activated] policy={} entry_id={entry_id}",
+        policy.as_str()
+    );
+}
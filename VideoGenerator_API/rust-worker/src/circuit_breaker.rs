@@ -0,0 +1,166 @@
+//! Circuit breaker around `run_python_for`, so a flapping downstream model
+//! runner doesn't burn the whole backlog into the `failed` state.
+//!
+//! - Closed: jobs run normally; consecutive failures/timeouts are counted.
+//! - Open: once `threshold` consecutive failures are seen, reject new job
+//!   attempts outright for `cooldown`, instead of spending `RUNNER_TIMEOUT_S`
+//!   discovering each one still fails.
+//! - Half-open: once `cooldown` elapses, let exactly one probe attempt
+//!   through. Success closes the breaker; failure reopens it for another
+//!   `cooldown`.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Admission {
+    /// Breaker is closed; proceed normally.
+    Allowed,
+    /// Cooldown elapsed; this call is the single half-open probe.
+    AllowedAsProbe,
+    /// Breaker is open (or a probe is already in flight); skip this attempt.
+    Rejected,
+}
+
+enum State {
+    Closed { consecutive_failures: u32 },
+    Open { opened_at: Instant },
+    HalfOpen,
+}
+
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<State>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            state: Mutex::new(State::Closed {
+                consecutive_failures: 0,
+            }),
+        }
+    }
+
+    /// Call before attempting a job run.
+    pub fn admit(&self) -> Admission {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match *state {
+            State::Closed { .. } => Admission::Allowed,
+            State::Open { opened_at } => {
+                if opened_at.elapsed() >= self.cooldown {
+                    *state = State::HalfOpen;
+                    Admission::AllowedAsProbe
+                } else {
+                    Admission::Rejected
+                }
+            }
+            State::HalfOpen => Admission::Rejected,
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if !matches!(*state, State::Closed { consecutive_failures: 0 }) {
+            eprintln!("[circuit_breaker.closed]");
+        }
+        *state = State::Closed {
+            consecutive_failures: 0,
+        };
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        *state = match *state {
+            State::Closed {
+                consecutive_failures,
+            } => {
+                let failures = consecutive_failures + 1;
+                if failures >= self.threshold {
+                    eprintln!("[circuit_breaker.open] consecutive_failures={failures}");
+                    State::Open {
+                        opened_at: Instant::now(),
+                    }
+                } else {
+                    State::Closed {
+                        consecutive_failures: failures,
+                    }
+                }
+            }
+            State::Open { opened_at } => State::Open { opened_at },
+            State::HalfOpen => {
+                eprintln!("[circuit_breaker.reopen] probe attempt failed");
+                State::Open {
+                    opened_at: Instant::now(),
+                }
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stays_closed_and_allowed_below_the_failure_threshold() {
+        let breaker = CircuitBreaker::new(3, Duration::from_millis(50));
+        breaker.record_failure();
+        breaker.record_failure();
+
+        assert_eq!(breaker.admit(), Admission::Allowed);
+    }
+
+    #[test]
+    fn opens_and_rejects_once_the_threshold_is_reached() {
+        let breaker = CircuitBreaker::new(3, Duration::from_millis(50));
+        breaker.record_failure();
+        breaker.record_failure();
+        breaker.record_failure();
+
+        assert_eq!(breaker.admit(), Admission::Rejected);
+    }
+
+    #[test]
+    fn a_success_resets_the_consecutive_failure_count() {
+        let breaker = CircuitBreaker::new(3, Duration::from_millis(50));
+        breaker.record_failure();
+        breaker.record_failure();
+        breaker.record_success();
+        breaker.record_failure();
+        breaker.record_failure();
+
+        // Only 2 consecutive failures since the reset — still under threshold.
+        assert_eq!(breaker.admit(), Admission::Allowed);
+    }
+
+    #[tokio::test]
+    async fn half_open_probe_closes_the_breaker_on_success() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(10));
+        breaker.record_failure();
+        assert_eq!(breaker.admit(), Admission::Rejected);
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(breaker.admit(), Admission::AllowedAsProbe);
+
+        breaker.record_success();
+        assert_eq!(breaker.admit(), Admission::Allowed);
+    }
+
+    #[tokio::test]
+    async fn half_open_probe_reopens_the_breaker_on_failure() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(10));
+        breaker.record_failure();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(breaker.admit(), Admission::AllowedAsProbe);
+
+        breaker.record_failure();
+        assert_eq!(breaker.admit(), Admission::Rejected, "a failed probe must reopen, not close, the breaker");
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(breaker.admit(), Admission::AllowedAsProbe, "cooldown restarted from the reopen");
+    }
+}
@@ -0,0 +1,185 @@
+//! Redlock-style single-instance distributed lock guarding job execution.
+//!
+//! Guards each `jid` so at most one `rust_worker` instance runs
+//! `run_python_for` at a time, even when the same stream entry is
+//! redelivered to multiple workers before `mark_completed` lands. For a
+//! single Redis instance this reduces to `SET ... NX PX` on acquire and a
+//! compare-and-delete Lua script on release, so we never delete a lock we
+//! no longer own. This is genuine mutual exclusion layered on top of the
+//! existing idempotency markers (`is_completed` / `mark_processing`).
+
+use anyhow::Result;
+use redis::Script;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const LOCK_KEY_NS: &str = "videogen:lock";
+
+/// Release the lock only if we still hold it (token matches).
+const RELEASE_SCRIPT: &str = r#"
+if redis.call('get', KEYS[1]) == ARGV[1] then
+    return redis.call('del', KEYS[1])
+else
+    return 0
+end
+"#;
+
+/// Extend the TTL only if we still hold it (token matches).
+const RENEW_SCRIPT: &str = r#"
+if redis.call('get', KEYS[1]) == ARGV[1] then
+    return redis.call('pexpire', KEYS[1], ARGV[2])
+else
+    return 0
+end
+"#;
+
+/// A held lock on `jid`. Spawn a watchdog via `spawn_watchdog` for any job
+/// that may run longer than the lock TTL, and always end its life with
+/// `release` (dropping it bare leaves the key to expire on its own, which
+/// is safe but wastes the remainder of the TTL).
+pub struct JobLock {
+    key: String,
+    token: String,
+    ttl_ms: i64,
+    lost: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+    watchdog: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl JobLock {
+    /// Try to acquire the lock for `jid`. Returns `Ok(None)` (not an error)
+    /// if another worker currently holds it — callers should skip the entry
+    /// without advancing `last_id` so a later pass retries.
+    pub async fn try_acquire(
+        con: &mut redis::aio::MultiplexedConnection,
+        jid: &str,
+        ttl_ms: i64,
+    ) -> Result<Option<JobLock>> {
+        let key = format!("{LOCK_KEY_NS}:{jid}");
+        let token = random_token();
+
+        let acquired: Option<String> = redis::cmd("SET")
+            .arg(&key)
+            .arg(&token)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl_ms)
+            .query_async(con)
+            .await?;
+
+        if acquired.is_none() {
+            return Ok(None);
+        }
+
+        Ok(Some(JobLock {
+            key,
+            token,
+            ttl_ms,
+            lost: Arc::new(AtomicBool::new(false)),
+            stop: Arc::new(AtomicBool::new(false)),
+            watchdog: None,
+        }))
+    }
+
+    /// Spawn a watchdog task that re-extends the TTL at roughly `ttl/3`
+    /// intervals on `con`. If a renewal ever comes back empty (CAS mismatch
+    /// — we no longer hold the key), `lost()` flips true and the caller must
+    /// abort side effects. `con` is a clone of the caller's own connection
+    /// (cheap — `MultiplexedConnection` clones share one underlying
+    /// multiplexed connection) rather than a fresh one dialed per job: this
+    /// runs once per job at whatever throughput the worker pool sustains, so
+    /// paying a new handshake every time would add up fast.
+    pub fn spawn_watchdog(&mut self, mut con: redis::aio::MultiplexedConnection) {
+        let key = self.key.clone();
+        let token = self.token.clone();
+        let ttl_ms = self.ttl_ms;
+        let lost = Arc::clone(&self.lost);
+        let stop = Arc::clone(&self.stop);
+
+        let handle = tokio::spawn(async move {
+            let interval = Duration::from_millis((ttl_ms / 3).max(50) as u64);
+            let script = Script::new(RENEW_SCRIPT);
+            loop {
+                tokio::time::sleep(interval).await;
+                if stop.load(Ordering::SeqCst) {
+                    return;
+                }
+                let renewed: i64 = script
+                    .key(&key)
+                    .arg(&token)
+                    .arg(ttl_ms)
+                    .invoke_async(&mut con)
+                    .await
+                    .unwrap_or(0);
+                if renewed == 0 {
+                    eprintln!("[lock.watchdog.lost] key={key}");
+                    lost.store(true, Ordering::SeqCst);
+                    return;
+                }
+            }
+        });
+        self.watchdog = Some(handle);
+    }
+
+    /// True once the watchdog has observed that we no longer hold the lock
+    /// (or could not reach Redis to check). Callers must treat this as a
+    /// signal to kill any in-flight side effects rather than complete them.
+    pub fn lost(&self) -> bool {
+        self.lost.load(Ordering::SeqCst)
+    }
+
+    /// Stop the watchdog and release the lock with a compare-and-delete, so
+    /// we never delete a lock some other worker has since acquired.
+    ///
+    /// The watchdog only notices `stop` after it wakes from its next
+    /// `ttl_ms/3` sleep, so we must not block on its `JoinHandle` here — every
+    /// `release` call sits on the hot path of `process_entry` and awaiting it
+    /// would stall the calling worker for up to `ttl_ms/3` on every single
+    /// job. Nothing after `release()` reads `lost()`, so it is safe to just
+    /// flip `stop` and let the watchdog task wind itself down in the
+    /// background.
+    pub async fn release(mut self, con: &mut redis::aio::MultiplexedConnection) {
+        self.stop.store(true, Ordering::SeqCst);
+        self.watchdog.take();
+        if self.lost() {
+            // Already lost the CAS race; nothing of ours left to delete.
+            return;
+        }
+        let script = Script::new(RELEASE_SCRIPT);
+        if let Err(e) = script
+            .key(&self.key)
+            .arg(&self.token)
+            .invoke_async::<i64>(con)
+            .await
+        {
+            eprintln!("[lock.release.error] key={} err={e}", self.key);
+        }
+    }
+}
+
+impl Drop for JobLock {
+    /// Stop the watchdog, so a lock dropped without `release` is no longer
+    /// renewed and its key expires at the end of the current TTL.
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Opaque-enough fencing token: pid + wall-clock nanos + a per-thread
+/// counter, which is sufficient to make our CAS scripts safe without
+/// pulling in a dedicated RNG dependency.
+fn random_token() -> String {
+    thread_local!(static COUNTER: Cell<u64> = const { Cell::new(0) });
+    let seq = COUNTER.with(|c| {
+        let v = c.get().wrapping_add(1);
+        c.set(v);
+        v
+    });
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    format!("{:x}-{:x}-{:x}", std::process::id(), nanos, seq)
+}
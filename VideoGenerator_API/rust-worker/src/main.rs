@@ -13,12 +13,74 @@
 //! - Before heavy side-effects, we write a **processing checkpoint** keyed by entry_id & jid.
 //! - After success, we write a **completed marker** (and job status).
 //! - The handler must check existing markers to be **idempotent** across replays.
+//! - A fixed-capacity in-process `marker_cache::MarkerCache` (see that module)
+//!   sits in front of the Redis completed check: a positive hit this process
+//!   itself observed short-circuits without a network call, while misses and
+//!   anything else still fall through to Redis, which stays authoritative
+//!   across workers. Size it via `MARKER_CACHE_CAPACITY`.
+//! - All of the above goes through `checkpoint::CheckpointStore` rather than
+//!   a hard-wired `redis::Connection`, so the idempotency flow (skip if
+//!   completed, checkpoint before running, complete on success) can be
+//!   exercised in tests against `checkpoint::MockCheckpointStore` without a
+//!   live Redis.
+//!
+//! SINGLE-FLIGHT LOCKING (see `lock` module):
+//! - Idempotency markers stop us from *recording* a job twice, but two workers can still
+//!   race to *run* `run_python_for` for the same jid concurrently (e.g. a crash before
+//!   `mark_completed` followed by redelivery to another instance). `lock::JobLock` adds a
+//!   genuine Redlock-style mutual-exclusion layer on top: a worker that loses the race
+//!   skips the entry without advancing `last_id`, and a watchdog task re-extends the
+//!   lease while the job runs so a long-running job doesn't lose its lock mid-flight.
 //!
 //! BATCHING & BACKPRESSURE:
 //! - Use XREAD COUNT=N to fetch small batches; persist `last_id` as we advance through the batch.
 //! - TODO(backpressure): If jobs are large, consider *smaller COUNT* and/or *streaming I/O*
 //!   to avoid memory spikes during large payload processing.
 //!
+//! LARGE PAYLOADS (see `streaming` module):
+//! - A stream entry's `payload` field is only ever small: at or below `MAX_INLINE_PAYLOAD_BYTES`
+//!   it is used inline, and above that the producer is expected to write the content to a
+//!   separate Redis key and put a `ref:<key>` pointer in the entry instead. Referenced
+//!   payloads are pulled via `GETRANGE` in reused `streaming::CHUNK_BYTES` chunks rather
+//!   than one growing allocation, so memory stays bounded regardless of input size.
+//!
+//! READER/WORKER POOL (see `workerpool` module):
+//! - A single reader task issues XREAD and decodes entries onto a bounded `flume` channel;
+//!   `WORKER_CONCURRENCY` worker tasks pull from it and run jobs independently, so one slow
+//!   GPU job no longer blocks later reads or XTRIM. The channel bound is the backpressure knob:
+//!   a full channel blocks the reader, not the workers.
+//! - Because workers finish out of order, `last_id` is **not** just "the entry we last read" —
+//!   `workerpool::CompletionTracker` tracks read order and only lets us persist the highest
+//!   contiguous *processed* ID, so we never skip an entry that is still outstanding.
+//! - `process_entry` reports contention it never got to run the handler over (lock busy, a
+//!   Redis error from the `is_completed` check, a lock lost mid-run) as `ProcessOutcome::Retry`
+//!   rather than marking the entry done: the worker loop requeues the `WorkItem` (backed off via
+//!   `backoff::next_delay`) up to `WORKITEM_REQUEUE_MAX_ATTEMPTS` times before giving up and
+//!   letting the tracker advance past it anyway, so one stuck entry can't pin `last_id` and the
+//!   tracker's memory forever. Errors *inside* the handler itself are a separate concern:
+//!   `run_python_for` failures are retried up to `JOB_MAX_RETRIES` times (see below), payload
+//!   staging errors are not retried, and both resolve to `ProcessOutcome::Done` + a permanent
+//!   `failed` status.
+//!
+//! BACKPRESSURE POLICY (see `backpressure` module):
+//! - `BACKPRESSURE_POLICY=block` (default) simply blocks the reader when the channel is full.
+//! - `drop_oldest` sacrifices the oldest unclaimed queued entry to make room, and records it
+//!   to a side "skipped" marker for audit.
+//! - `shed` stops accepting new entries until the channel has room again, leaving them unread
+//!   on the stream so Redis (and the existing MINID retention window) buffers them instead.
+//! - Whichever policy is active, activation is logged as an explicit, observable event.
+//!
+//! TIMEOUTS & CIRCUIT BREAKING (see `circuit_breaker` and `backoff` modules):
+//! - `run_python_for` runs under `tokio::process::Command` + `tokio::time::timeout`, so a
+//!   hung subprocess is killed and reaped cleanly instead of busy-polling `try_wait`.
+//! - `circuit_breaker::CircuitBreaker` tracks consecutive `run_python_for` failures/timeouts;
+//!   once `CIRCUIT_BREAKER_THRESHOLD` is crossed it opens and rejects new attempts for
+//!   `CIRCUIT_BREAKER_COOLDOWN_MS`, then allows a single half-open probe, so a flapping model
+//!   runner doesn't burn the whole backlog into `failed`.
+//! - `backoff::next_delay` (exponential + jittered) backs off both the XREAD-level reconnect
+//!   path (`connect_with_backoff`) and in-process job retries (`JOB_MAX_RETRIES`) before a
+//!   job is finally reported failed.
+//!
 //! ERROR HANDLING:
 //! - Never `unwrap()` on untrusted payloads; use lossy UTF-8 fallback or keep raw bytes.
 //!
@@ -30,8 +92,8 @@
 //! - Default: keep ~120 minutes (TRIM_MINUTES). This is a pragmatic default for text→video
 //!   jobs on GPU clusters (e.g., NVIDIA H100/HGX). If your pipeline has long queues or
 //!   slow post-processing, consider:
-//!     • 30–60 min for high-throughput, low-latency services (better memory profile).
-//!     • 180–240+ min for bursty workloads or multi-stage pipelines (safer for audits/replays).
+//!   • 30–60 min for high-throughput, low-latency services (better memory profile).
+//!   • 180–240+ min for bursty workloads or multi-stage pipelines (safer for audits/replays).
 //!   Pick a value that covers your **worst-case redelivery window**, audit needs, and cost.
 //!
 //! ACCESS CONTROL (TODO):
@@ -44,13 +106,6 @@
 //!   loop.lag_ms, stream.approx_queue_depth (estimate via last_id deltas / XINFO STREAM).
 //! - Correlation IDs in logs: include both {entry_id, jid} for traceability.
 //!
-//! PERFORMANCE & RELIABILITY STRATEGIES (TODO):
-//! - Timeouts & circuit breaking: add subprocess timeouts (wait_timeout crate / tokio),
-//!   network I/O timeouts, retry with **exponential backoff + jitter**. If downstream flaps,
-//!   shed load temporarily with a circuit breaker.
-//! - Connection management: hold a long-lived Redis connection; on failure, **reconnect with backoff**.
-//!   If using TLS, Sentinel, or Cluster, wire up failover/retry logic accordingly.
-//!
 //! MAJOR CHANGES (vs. naive XREAD with ">"):
 //! - Use `XREAD ... STREAMS <stream> <last_id>` (NOT ">") and persist `last_id`.
 //! - Add **processing checkpoint** & **completed marker** for idempotency and stronger guarantees.
@@ -61,21 +116,33 @@
 //! - Start with `$` (new-only) and skip persistence. This is simplest but **drops** entries while offline,
 //!   provides only best-effort processing, and is not suitable for most production pipelines.
 
+mod backoff;
+mod backpressure;
+mod checkpoint;
+mod circuit_breaker;
+mod lock;
+mod marker_cache;
+mod streaming;
+mod workerpool;
+
 use anyhow::{bail, Context, Result};
-use redis::Commands;
-use std::process::{Command, Stdio};
-use std::thread;
+use backpressure::BackpressurePolicy;
+use checkpoint::CheckpointStore;
+use circuit_breaker::CircuitBreaker;
+use lock::JobLock;
+use marker_cache::MarkerCache;
+use redis::AsyncCommands;
+use std::fs;
+use std::process::Stdio;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use streaming::Payload;
+use tokio::process::Command;
+use workerpool::{CompletionTracker, WorkItem};
 
-/// Keys & defaults
-const LAST_ID_KEY: &str = "videogen:last_id"; // persisted last seen stream ID
-const PROCESSING_KEY_NS: &str = "videogen:processing"; // Namespace for processing checkpoints
-const COMPLETED_KEY_NS: &str = "videogen:completed"; // Namespace for completed markers
-const PROCESSING_TTL_SECS: i64 = 24 * 60 * 60; // expire processing markers after 24h
-const COMPLETED_TTL_SECS: u64 = 7 * 24 * 60 * 60; // keep completion markers for a week
-const RETRY_BACKOFF_ON_ERROR_MS: u64 = 250; // small pause before retrying a failing job
-
-fn main() -> Result<()> {
+#[tokio::main]
+async fn main() -> Result<()> {
     // ---------- Configuration ----------
     let redis_url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://redis:6379/0".into());
     let stream_name = std::env::var("JOBS_STREAM").unwrap_or_else(|_| "videogen:jobs".into());
@@ -108,26 +175,214 @@ fn main() -> Result<()> {
         .and_then(|s| s.parse().ok())
         .unwrap_or(80);
 
-    // Subprocess timeout (seconds) — implement with wait_timeout/tokio in real code.
+    // Subprocess timeout: run_python_for is cancelled via tokio::time::timeout.
     let runner_timeout_s: u64 = std::env::var("RUNNER_TIMEOUT_S")
         .ok()
         .and_then(|s| s.parse().ok())
         .unwrap_or(600);
 
+    // Single-flight lock: guards against two workers running run_python_for
+    // for the same jid concurrently (duplicate GPU work, racing job:{jid} writes).
+    let job_lock_ttl_ms: i64 = std::env::var("JOB_LOCK_TTL_MS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(30_000);
+
+    // Worker pool sizing: how many jobs run concurrently, and how many decoded
+    // entries the reader is allowed to queue up before XREAD itself blocks.
+    let worker_concurrency: usize = std::env::var("WORKER_CONCURRENCY")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(4);
+    let channel_capacity: usize = std::env::var("CHANNEL_CAPACITY")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(64);
+
+    // Payloads at or below this size ride inline in the stream entry; larger
+    // ones must be written by the producer to a separate key and referenced
+    // from the entry as `ref:<key>`, then pulled via streaming::for_each_record.
+    let max_inline_payload_bytes: usize = std::env::var("MAX_INLINE_PAYLOAD_BYTES")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(4096);
+
+    // What to do when the worker-pool channel saturates: pause XREAD (block),
+    // sacrifice the oldest queued entry (drop_oldest), or stop accepting new
+    // entries and let them sit unread on the stream (shed).
+    let backpressure_policy = std::env::var("BACKPRESSURE_POLICY")
+        .ok()
+        .map(|s| {
+            BackpressurePolicy::from_str(&s).unwrap_or_else(|e| {
+                eprintln!("[config.backpressure_policy.invalid] {e}; defaulting to block");
+                BackpressurePolicy::Block
+            })
+        })
+        .unwrap_or(BackpressurePolicy::Block);
+
+    // In-process cache of recently observed completed/processing markers, so
+    // hot replays and catch-up-from-"0-0" runs don't hammer Redis with
+    // idempotency lookups this process already knows the answer to.
+    let marker_cache_capacity: usize = std::env::var("MARKER_CACHE_CAPACITY")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(10_000);
+
+    // Circuit breaker: stop spawning new jobs once this many consecutive
+    // run_python_for attempts have failed/timed out, for a cooldown window.
+    let circuit_breaker_threshold: u32 = std::env::var("CIRCUIT_BREAKER_THRESHOLD")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(5);
+    let circuit_breaker_cooldown_ms: u64 = std::env::var("CIRCUIT_BREAKER_COOLDOWN_MS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(30_000);
+
+    // In-process job retries: how many extra attempts run_python_for gets
+    // (with backoff + jitter between them) before the entry is reported failed.
+    let job_max_retries: u32 = std::env::var("JOB_MAX_RETRIES")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(2);
+
+    // Pool-level requeues: how many times a `WorkItem` gets sent back through
+    // the channel after `process_entry` reports transient contention (lock
+    // busy, a Redis error, a lock lost mid-run) before we give up on it and
+    // let the completion tracker advance past it anyway. Unlike
+    // `job_max_retries` above (retries of the handler itself, within one
+    // `process_entry` call), this bounds how long an entry can pin
+    // `CompletionTracker`'s read-order prefix.
+    let workitem_requeue_max_attempts: u32 = std::env::var("WORKITEM_REQUEUE_MAX_ATTEMPTS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(5);
+
     // ---------- Connection (long-lived; reconnect with backoff on failure) ----------
     let client = redis::Client::open(redis_url)?;
-    let mut con = connect_with_backoff(&client)?;
+    let mut con = connect_with_backoff(&client).await?;
 
     // ---------- Load persisted last_id or use startup_id ----------
-    let mut last_id = redis_get_string(&mut con, LAST_ID_KEY)?.unwrap_or(startup_id);
+    let last_id = con.load_last_id().await?.unwrap_or(startup_id);
+
+    // ---------- Worker pool ----------
+    // Reader decodes entries and pushes them here; workers pull and process.
+    // A bounded channel means a burst of slow jobs backs up the channel, which
+    // blocks `tx.send` in the reader and in turn pauses XREAD — the channel
+    // bound *is* the backpressure knob.
+    let (tx, rx) = flume::bounded::<WorkItem>(channel_capacity);
+    // A second receiver handle kept by the reader itself (never iterated),
+    // purely so `drop_oldest` can evict the front of the queue without the
+    // workers' receivers being involved.
+    let reader_rx = rx.clone();
+    let tracker = Arc::new(Mutex::new(CompletionTracker::new()));
+    let marker_cache = Arc::new(Mutex::new(MarkerCache::new(marker_cache_capacity)));
+    let breaker = Arc::new(CircuitBreaker::new(
+        circuit_breaker_threshold,
+        Duration::from_millis(circuit_breaker_cooldown_ms),
+    ));
+
+    for worker_id in 0..worker_concurrency {
+        let rx = rx.clone();
+        let tx = tx.clone();
+        let client = client.clone();
+        let tracker = Arc::clone(&tracker);
+        let marker_cache = Arc::clone(&marker_cache);
+        let breaker = Arc::clone(&breaker);
+        tokio::spawn(async move {
+            let mut con = match connect_with_backoff(&client).await {
+                Ok(c) => c,
+                Err(e) => {
+                    eprintln!("[worker.connect.error] worker_id={worker_id} err={e}");
+                    return;
+                }
+            };
+            while let Ok(item) = rx.recv_async().await {
+                let outcome = process_entry(
+                    &mut con,
+                    &item.entry_id,
+                    &item.jid,
+                    &item.payload,
+                    runner_timeout_s,
+                    job_lock_ttl_ms,
+                    max_inline_payload_bytes,
+                    job_max_retries,
+                    &marker_cache,
+                    &breaker,
+                )
+                .await;
+                match outcome {
+                    ProcessOutcome::Done => {
+                        advance_tracker(&tracker, &mut con, &item.entry_id).await;
+                    }
+                    ProcessOutcome::Retry if item.requeue_count < workitem_requeue_max_attempts => {
+                        // Transient contention, not a permanent failure — requeue
+                        // rather than mark done, so `CompletionTracker` still
+                        // treats this entry as outstanding and `last_id` never
+                        // advances past it. Backed off the same way as
+                        // `run_python_with_retries`'s in-handler attempts.
+                        //
+                        // Spawned as its own task rather than awaited inline so
+                        // this worker goes straight back to `rx.recv_async()`
+                        // instead of sitting idle for the backoff window (up to
+                        // 30s) — otherwise a burst of contended entries would
+                        // starve the whole pool of capacity for new work right
+                        // when it's most needed.
+                        let delay = backoff::next_delay(
+                            item.requeue_count,
+                            Duration::from_millis(250),
+                            Duration::from_secs(30),
+                        );
+                        eprintln!(
+                            "[workitem.requeue] entry_id={} jid={} attempt={} delay_ms={}",
+                            item.entry_id,
+                            item.jid,
+                            item.requeue_count,
+                            delay.as_millis()
+                        );
+                        let mut item = item;
+                        item.requeue_count += 1;
+                        let tx = tx.clone();
+                        tokio::spawn(async move {
+                            tokio::time::sleep(delay).await;
+                            if tx.send_async(item).await.is_err() {
+                                eprintln!("[workitem.requeue.error] reader has exited");
+                            }
+                        });
+                    }
+                    ProcessOutcome::Retry => {
+                        // Exhausted the requeue budget: give up rather than pin
+                        // CompletionTracker's prefix forever. The entry is not
+                        // recorded as succeeded (job:{jid} status is whatever
+                        // process_entry last left it as), but last_id can still
+                        // advance past it.
+                        eprintln!(
+                            "[workitem.requeue.abandoned] entry_id={} jid={} attempts={}",
+                            item.entry_id, item.jid, item.requeue_count
+                        );
+                        advance_tracker(&tracker, &mut con, &item.entry_id).await;
+                    }
+                }
+            }
+        });
+    }
+    // Note: each worker keeps its own `tx` clone alive for its entire
+    // lifetime (needed to requeue contended items onto detached backoff
+    // tasks above), so the reader's own `tx` going away does NOT make
+    // `recv_async` return `Err` once the reader loop exits — this process
+    // has no graceful worker-shutdown path today, so that gap is latent
+    // rather than active. Only the original receiver handle is dropped here;
+    // `reader_rx` stays alive for `drop_oldest`.
+    drop(rx);
 
-    // simple loop counters for periodic tasks
+    // ---------- Reader: XREAD + decode only; never blocks on job execution ----------
+    let mut read_cursor = last_id;
     let mut loop_count: u64 = 0;
 
     loop {
         loop_count += 1;
 
-        // --- XREAD: fetch up to COUNT entries after last_id; BLOCK up to block_ms ---
+        // --- XREAD: fetch up to COUNT entries after read_cursor; BLOCK up to block_ms ---
         let resp = redis::cmd("XREAD")
             .arg("BLOCK")
             .arg(block_ms)
@@ -135,15 +390,16 @@ fn main() -> Result<()> {
             .arg(count)
             .arg("STREAMS")
             .arg(&stream_name)
-            .arg(&last_id) // strictly AFTER this ID
-            .query::<redis::Value>(&mut con);
+            .arg(&read_cursor) // strictly AFTER this ID
+            .query_async::<redis::Value>(&mut con)
+            .await;
 
         let value = match resp {
             Ok(v) => v,
             Err(e) => {
-                eprintln!("[xread.error] err={e} last_id={last_id}");
+                eprintln!("[xread.error] err={e} read_cursor={read_cursor}");
                 // reconnect with backoff, then continue loop
-                con = connect_with_backoff(&client)?;
+                con = connect_with_backoff(&client).await?;
                 continue;
             }
         };
@@ -151,9 +407,8 @@ fn main() -> Result<()> {
         // RESPONSE SHAPE: [[stream, [[id, [k,v,k,v,...]], ...]]]
         if let redis::Value::Array(streams) = value {
             let mut advanced_any = false;
-            let mut had_batch_error = false;
 
-            'stream_loop: for s in streams {
+            'streams: for s in streams {
                 let Some(parts) = as_bulk(&s) else { continue };
                 if parts.len() != 2 {
                     continue;
@@ -170,7 +425,7 @@ fn main() -> Result<()> {
 
                     // ----- Extract entry_id -----
                     let entry_id = ev
-                        .get(0)
+                        .first()
                         .and_then(as_data)
                         .map(|b| {
                             String::from_utf8(b.to_vec())
@@ -179,14 +434,17 @@ fn main() -> Result<()> {
                         .unwrap_or_default();
 
                     if entry_id.is_empty() {
-                        // malformed; skip but do NOT advance last_id
-                        eprintln!("[entry.malformed] missing id; last_id={last_id}");
+                        // malformed; skip but do NOT advance read_cursor
+                        eprintln!("[entry.malformed] missing id; read_cursor={read_cursor}");
                         continue;
                     }
 
                     // ----- Extract fields -----
                     // Expect a Bulk([k,v,k,v,...]) at ev[1]. We use lossy UTF-8 to avoid panics.
+                    // `payload` is intentionally captured as raw bytes, never grown beyond what
+                    // the producer put in the entry itself — see the LARGE PAYLOADS note above.
                     let mut jid = String::new();
+                    let mut payload: Vec<u8> = Vec::new();
                     if let Some(kv) = ev.get(1).and_then(as_bulk) {
                         for i in (0..kv.len()).step_by(2) {
                             if let (Some(k), Some(v)) = (kv.get(i), kv.get(i + 1)) {
@@ -195,146 +453,409 @@ fn main() -> Result<()> {
                                         jid = String::from_utf8(vb.to_vec()).unwrap_or_else(|_| {
                                             String::from_utf8_lossy(vb).into_owned()
                                         });
+                                    } else if kb == b"payload" {
+                                        payload = vb.to_vec();
                                     }
                                 }
                             }
                         }
                     }
 
-                    // Use both entry_id & jid (if present) as correlation IDs in logs/metrics.
-                    let corr = if jid.is_empty() {
-                        format!("entry_id={entry_id}")
-                    } else {
-                        format!("entry_id={entry_id} jid={jid}")
-                    };
+                    if jid.is_empty() {
+                        eprintln!("[entry.malformed] entry_id={entry_id} missing job id");
+                        read_cursor = entry_id.clone();
+                        advanced_any = true;
+                        {
+                            let mut guard = tracker.lock().unwrap_or_else(|e| e.into_inner());
+                            guard.submit(entry_id.clone());
+                        }
+                        advance_tracker(&tracker, &mut con, &entry_id).await;
+                        continue;
+                    }
 
-                    let mut advance_last_id = false;
-                    let mut fatal_error = false;
+                    // `shed` is checked *before* we commit to this entry: if the
+                    // channel is already full, leave the cursor where it was (so
+                    // the next XREAD sees this entry again) and stop consuming the
+                    // rest of this batch — Redis's MINID retention window buffers
+                    // what we didn't read, instead of us buffering it in memory.
+                    if backpressure_policy == BackpressurePolicy::Shed && tx.is_full() {
+                        backpressure::log_activated(backpressure_policy, &entry_id);
+                        break 'streams;
+                    }
 
-                    if jid.is_empty() {
-                        eprintln!("[entry.malformed] {corr} missing job id");
-                        advance_last_id = true;
-                    } else if is_completed(&mut con, &entry_id, jid.as_str())? {
-                        eprintln!("[handler.skip.completed] {corr}");
-                        advance_last_id = true;
-                    } else {
-                        // ----- PROCESSING CHECKPOINT -----
-                        // For stronger guarantees/idempotency:
-                        // - Write a checkpoint *before* heavy side effects.
-                        // - If we crash/replay, handler sees checkpoint/completed markers and acts idempotently.
-                        if let Err(e) = mark_processing(&mut con, &entry_id, jid.as_str()) {
-                            eprintln!("[processing.mark.error] {corr} err={e}");
-                            // If we cannot mark processing, do not proceed; we'll see it again.
-                            fatal_error = true;
-                        } else {
-                            // Mark job hash "status=processing" best-effort (not a hard precondition).
-                            if let Err(e) = con.hset::<_, _, _, ()>(
-                                format!("job:{jid}"),
-                                "status",
-                                "processing",
-                            ) {
-                                eprintln!("[job.status.mark.error] {corr} err={e}");
+                    // The reader's cursor moves forward as soon as an entry is read,
+                    // regardless of how it is eventually processed — that's what lets
+                    // a slow job downstream avoid blocking later reads. The *persisted*
+                    // last_id is handled separately by the completion tracker.
+                    read_cursor = entry_id.clone();
+                    advanced_any = true;
+
+                    {
+                        let mut guard = tracker.lock().unwrap_or_else(|e| e.into_inner());
+                        guard.submit(entry_id.clone());
+                    }
+
+                    let item = WorkItem {
+                        entry_id,
+                        jid,
+                        payload,
+                        requeue_count: 0,
+                    };
+
+                    match backpressure_policy {
+                        // `block` always blocks on a full channel; `shed` already
+                        // confirmed the channel was not full above (a true race
+                        // with a worker draining a slot is harmless either way),
+                        // so both hand off the same way from here.
+                        BackpressurePolicy::Block | BackpressurePolicy::Shed => {
+                            if backpressure_policy == BackpressurePolicy::Block && tx.is_full() {
+                                backpressure::log_activated(backpressure_policy, &item.entry_id);
                             }
-                            if let Err(e) = con.hset::<_, _, _, ()>(
-                                format!("job:{jid}"),
-                                "processing_entry_id",
-                                &entry_id,
-                            ) {
-                                eprintln!("[job.processing_entry.mark.error] {corr} err={e}");
+                            if tx.send_async(item).await.is_err() {
+                                eprintln!("[workerpool.send.error] all workers have exited");
                             }
-
-                            match run_python_for(&mut con, jid.as_str(), runner_timeout_s) {
-                                Ok(()) => {
-                                    let _ = mark_completed(&mut con, &entry_id, jid.as_str());
-                                    if let Err(e) = con.hset::<_, _, _, ()>(
-                                        format!("job:{jid}"),
-                                        "status",
-                                        "completed",
-                                    ) {
-                                        eprintln!("[job.status.completed.error] {corr} err={e}");
-                                    }
-                                    advance_last_id = true;
-                                    // Telemetry (TODO): increment jobs.processed; record latency histogram
-                                }
-                                Err(e) => {
-                                    eprintln!("[handler.error] {corr} err={e}");
-                                    if let Err(err) = con.hset::<_, _, _, ()>(
-                                        format!("job:{jid}"),
-                                        "status",
-                                        "failed",
-                                    ) {
-                                        eprintln!("[job.status.failed.error] {corr} err={err}");
+                        }
+                        BackpressurePolicy::DropOldest => {
+                            if let Err(e) = tx.try_send(item) {
+                                match e {
+                                    flume::TrySendError::Full(item) => {
+                                        backpressure::log_activated(backpressure_policy, &item.entry_id);
+                                        // Sacrifice the oldest entry still sitting unclaimed in
+                                        // the channel to make room for this one.
+                                        if let Ok(evicted) = reader_rx.try_recv() {
+                                            if let Err(e) = backpressure::record_skipped(
+                                                &mut con,
+                                                &evicted.entry_id,
+                                                &evicted.jid,
+                                            )
+                                            .await
+                                            {
+                                                eprintln!(
+                                                    "[backpressure.record_skipped.error] entry_id={} err={e}",
+                                                    evicted.entry_id
+                                                );
+                                            }
+                                            advance_tracker(&tracker, &mut con, &evicted.entry_id).await;
+                                        }
+                                        // There is now room for exactly the one entry we just
+                                        // evicted; fall back to a blocking send so a worker
+                                        // race doesn't strand this item.
+                                        if tx.send_async(item).await.is_err() {
+                                            eprintln!(
+                                                "[workerpool.send.error] all workers have exited"
+                                            );
+                                        }
                                     }
-                                    if let Err(err) = con.hset::<_, _, _, ()>(
-                                        format!("job:{jid}"),
-                                        "error",
-                                        e.to_string(),
-                                    ) {
-                                        eprintln!("[job.error.write.error] {corr} err={err}");
+                                    flume::TrySendError::Disconnected(_) => {
+                                        eprintln!("[workerpool.send.error] all workers have exited");
                                     }
-                                    fatal_error = true;
-                                    // Telemetry (TODO): increment jobs.failed
                                 }
                             }
                         }
                     }
-
-                    if advance_last_id {
-                        // ----- ADVANCE & PERSIST last_id after handling this entry -----
-                        last_id = entry_id.clone();
-                        if let Err(e) = con.set::<_, _, ()>(LAST_ID_KEY, &last_id) {
-                            eprintln!("[last_id.persist.error] id={last_id} err={e}");
-                            // Not fatal: we may reprocess on restart.
-                        }
-                        advanced_any = true;
-                    }
-
-                    if fatal_error {
-                        had_batch_error = true;
-                        break 'stream_loop;
-                    }
                 }
             }
 
             // Optional small sleep only if we got no data (reduce spin)
             if !advanced_any {
-                thread::sleep(Duration::from_millis(25));
-            }
-            if had_batch_error {
-                thread::sleep(Duration::from_millis(RETRY_BACKOFF_ON_ERROR_MS));
+                tokio::time::sleep(Duration::from_millis(25)).await;
             }
         } else {
             // XREAD timeout (no entries); small sleep to reduce CPU
-            thread::sleep(Duration::from_millis(10));
+            tokio::time::sleep(Duration::from_millis(10)).await;
         }
 
         // ----- Periodic trimming by MINID watermark -----
-        if loop_count % trim_every_n_loops == 0 {
-            if let Err(e) = trim_stream_minid(&mut con, &stream_name, &last_id, trim_minutes) {
+        // Trims relative to the reader's cursor, which runs ahead of the
+        // persisted completion watermark by every entry still queued or in
+        // flight; those entries stay on the stream only while they are newer
+        // than the trim_minutes cutoff.
+        if loop_count.is_multiple_of(trim_every_n_loops) {
+            if let Err(e) = trim_stream_minid(&mut con, &stream_name, &read_cursor, trim_minutes).await {
                 eprintln!("[trim.error] err={e}");
             }
         }
     }
 }
 
-/// Connect with simple exponential backoff.
-fn connect_with_backoff(client: &redis::Client) -> Result<redis::Connection> {
-    let mut delay = Duration::from_millis(200);
-    for _ in 0..8 {
-        match client.get_connection() {
+/// What the caller should do with a `WorkItem` once `process_entry` returns.
+enum ProcessOutcome {
+    /// Fully resolved — succeeded, skipped as already-completed, or failed
+    /// permanently after exhausting its own in-process retries (job status
+    /// is already written as `failed`). Safe to mark done in
+    /// `CompletionTracker` either way; there is nothing further to retry.
+    Done,
+    /// Transient contention: the lock was busy, a Redis call errored, or the
+    /// lock was lost mid-run to another worker. The entry itself was never
+    /// actually resolved — the caller should requeue the `WorkItem` (up to a
+    /// bounded number of attempts) rather than mark it done.
+    Retry,
+}
+
+/// Mark `entry_id` done in `tracker` and persist the new `last_id` if that
+/// let the contiguous completed prefix advance. Shared by every call site
+/// that resolves an entry one way or another (success, abandonment after
+/// exhausting requeue attempts, a malformed entry, or a `drop_oldest`
+/// eviction) so the tracker-lock/mark_done/advance/persist sequence lives in
+/// one place.
+async fn advance_tracker(
+    tracker: &Mutex<CompletionTracker>,
+    con: &mut redis::aio::MultiplexedConnection,
+    entry_id: &str,
+) {
+    // Scoped so the (non-`Send`) `MutexGuard` is dropped before the
+    // `store_last_id` await below.
+    let new_last_id = {
+        let mut guard = tracker.lock().unwrap_or_else(|e| e.into_inner());
+        guard.mark_done(entry_id);
+        guard.advance()
+    };
+    if let Some(new_last_id) = new_last_id {
+        if let Err(e) = con.store_last_id(&new_last_id).await {
+            eprintln!("[last_id.persist.error] id={new_last_id} err={e}");
+        }
+    }
+}
+
+/// Process a single decoded stream entry end-to-end: idempotency check,
+/// single-flight lock, processing checkpoint, job execution (with retries
+/// and circuit breaking), and completion marker. Returns `ProcessOutcome::Done`
+/// if the caller should advance past `entry_id` (success, already-completed,
+/// or permanently failed), `ProcessOutcome::Retry` if it must be requeued
+/// instead (lock contention, a transient Redis error, or a lock lost
+/// mid-run) so the completion tracker does not skip past it.
+#[allow(clippy::too_many_arguments)]
+async fn process_entry(
+    con: &mut redis::aio::MultiplexedConnection,
+    entry_id: &str,
+    jid: &str,
+    payload: &[u8],
+    runner_timeout_s: u64,
+    job_lock_ttl_ms: i64,
+    max_inline_payload_bytes: usize,
+    job_max_retries: u32,
+    marker_cache: &Mutex<MarkerCache>,
+    breaker: &CircuitBreaker,
+) -> ProcessOutcome {
+    let corr = format!("entry_id={entry_id} jid={jid}");
+
+    // A positive "completed" hit this process itself recorded short-circuits
+    // without touching Redis at all; anything else falls through to the
+    // existing EXISTS/SISMEMBER check, which stays authoritative across
+    // workers.
+    let cached_completed = marker_cache
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .is_completed(entry_id);
+
+    let completed = if cached_completed {
+        true
+    } else {
+        match con.is_completed(entry_id, jid).await {
+            Ok(c) => {
+                if c {
+                    marker_cache
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .mark_completed(entry_id);
+                }
+                c
+            }
+            Err(e) => {
+                eprintln!("[is_completed.error] {corr} err={e}");
+                return ProcessOutcome::Retry;
+            }
+        }
+    };
+    if completed {
+        eprintln!("[handler.skip.completed] {corr}");
+        return ProcessOutcome::Done;
+    }
+
+    // ----- SINGLE-FLIGHT LOCK -----
+    // Mutual exclusion on top of the idempotency markers above: if another
+    // worker already holds the lock for this jid, skip without advancing
+    // past it so the caller can requeue this entry for a later retry.
+    let mut job_lock = match JobLock::try_acquire(con, jid, job_lock_ttl_ms).await {
+        Ok(None) => {
+            eprintln!("[lock.busy] {corr}");
+            return ProcessOutcome::Retry;
+        }
+        Err(e) => {
+            eprintln!("[lock.acquire.error] {corr} err={e}");
+            return ProcessOutcome::Retry;
+        }
+        Ok(Some(job_lock)) => job_lock,
+    };
+    job_lock.spawn_watchdog(con.clone());
+
+    // ----- PROCESSING CHECKPOINT + EXECUTION -----
+    // `begin_checkpoint`/`finish_checkpoint` are the same two functions
+    // `checkpoint`'s tests drive against `MockCheckpointStore`, so a crash
+    // between checkpointing and completion, a duplicate redelivery, or a
+    // malformed entry behaves identically here and under test: write a
+    // checkpoint *before* heavy side effects, run the handler inline, and
+    // record completion only on success. They're split in two (rather than
+    // one "run the handler for me" helper) so the handler below can borrow
+    // `con` directly alongside `job_lock`, `marker_cache`, and the rest of
+    // this function's locals without a closure signature that can't express
+    // those non-`'static` captures.
+    match checkpoint::begin_checkpoint(con, entry_id, jid).await {
+        Ok(true) => {
+            eprintln!("[handler.skip.completed] {corr}");
+            job_lock.release(con).await;
+            return ProcessOutcome::Done;
+        }
+        Ok(false) => {}
+        Err(e) => {
+            eprintln!("[checkpoint.begin.error] {corr} err={e}");
+            job_lock.release(con).await;
+            return ProcessOutcome::Retry;
+        }
+    }
+
+    let handler_result: Result<()> = async {
+        marker_cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .mark_processing(entry_id);
+
+        // Mark job hash "status=processing" best-effort (not a hard precondition).
+        if let Err(e) = con.hset::<_, _, _, ()>(format!("job:{jid}"), "status", "processing").await {
+            eprintln!("[job.status.mark.error] {corr} err={e}");
+        }
+        if let Err(e) = con
+            .hset::<_, _, _, ()>(format!("job:{jid}"), "processing_entry_id", entry_id)
+            .await
+        {
+            eprintln!("[job.processing_entry.mark.error] {corr} err={e}");
+        }
+
+        if !payload.is_empty() {
+            stage_payload(con, jid, payload, max_inline_payload_bytes).await?;
+        }
+
+        run_python_with_retries(con, jid, runner_timeout_s, &job_lock, breaker, job_max_retries).await?;
+
+        if job_lock.lost() {
+            // Renewal failed mid-run: another worker may now hold the lock
+            // and could be running the same job. Bail so finish_checkpoint
+            // does not record completion; this entry will be seen as
+            // completed on replay once the lock's actual owner finishes.
+            bail!("lost job lock for jid={jid} mid-run");
+        }
+        Ok(())
+    }
+    .await;
+
+    let run_result = checkpoint::finish_checkpoint(con, entry_id, jid, handler_result).await;
+
+    let outcome = match run_result {
+        Ok(()) => {
+            marker_cache
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .mark_completed(entry_id);
+            if let Err(e) = con.hset::<_, _, _, ()>(format!("job:{jid}"), "status", "completed").await {
+                eprintln!("[job.status.completed.error] {corr} err={e}");
+            }
+            // Telemetry (TODO): increment jobs.processed; record latency histogram
+            ProcessOutcome::Done
+        }
+        Err(_e) if job_lock.lost() => {
+            // Another worker may now hold the lock (and may already be
+            // running or have finished this same jid) — requeue rather than
+            // treat this as resolved; the redelivery will see is_completed
+            // true and skip cleanly once that worker finishes.
+            eprintln!("[lock.lost.abort] {corr}");
+            ProcessOutcome::Retry
+        }
+        Err(e) => {
+            // run_python_with_retries already exhausted job_max_retries —
+            // this is a permanent failure, not contention, so there is
+            // nothing left to retry at the pool level.
+            eprintln!("[handler.error] {corr} err={e}");
+            if let Err(err) = con.hset::<_, _, _, ()>(format!("job:{jid}"), "status", "failed").await {
+                eprintln!("[job.status.failed.error] {corr} err={err}");
+            }
+            if let Err(err) = con
+                .hset::<_, _, _, ()>(format!("job:{jid}"), "error", e.to_string())
+                .await
+            {
+                eprintln!("[job.error.write.error] {corr} err={err}");
+            }
+            // Telemetry (TODO): increment jobs.failed
+            ProcessOutcome::Done
+        }
+    };
+
+    job_lock.release(con).await;
+    outcome
+}
+
+/// Run `run_python_for` up to `1 + max_retries` times, gated by `breaker` and
+/// backed off with `backoff::next_delay` between attempts, so a handful of
+/// transient failures don't immediately mark a job `failed` and a flapping
+/// runner doesn't keep getting hammered with fresh attempts.
+async fn run_python_with_retries(
+    con: &mut redis::aio::MultiplexedConnection,
+    jid: &str,
+    timeout_s: u64,
+    job_lock: &JobLock,
+    breaker: &CircuitBreaker,
+    max_retries: u32,
+) -> Result<()> {
+    let mut attempt = 0;
+    loop {
+        if job_lock.lost() {
+            bail!("lost job lock for jid={jid}, aborting before attempt {attempt}");
+        }
+
+        if breaker.admit() == circuit_breaker::Admission::Rejected {
+            bail!("circuit breaker open; refusing to run jid={jid}");
+        }
+
+        match run_python_for(con, jid, timeout_s, Some(job_lock)).await {
+            Ok(()) => {
+                breaker.record_success();
+                return Ok(());
+            }
+            Err(e) => {
+                breaker.record_failure();
+                if attempt >= max_retries || job_lock.lost() {
+                    return Err(e);
+                }
+                let delay = backoff::next_delay(attempt, Duration::from_millis(250), Duration::from_secs(30));
+                eprintln!(
+                    "[run_python_for.retry] jid={jid} attempt={attempt} err={e} delay_ms={}",
+                    delay.as_millis()
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Connect with exponential backoff + jitter.
+async fn connect_with_backoff(client: &redis::Client) -> Result<redis::aio::MultiplexedConnection> {
+    let base = Duration::from_millis(200);
+    let max = Duration::from_secs(5);
+    for attempt in 0..8 {
+        match client.get_multiplexed_tokio_connection().await {
             Ok(c) => return Ok(c),
             Err(e) => {
+                let delay = backoff::next_delay(attempt, base, max);
                 eprintln!(
                     "[redis.connect.retry] err={e} delay_ms={}",
                     delay.as_millis()
                 );
-                thread::sleep(delay);
-                delay = std::cmp::min(delay * 2, Duration::from_secs(5));
+                tokio::time::sleep(delay).await;
             }
         }
     }
     // final attempt
-    Ok(client.get_connection()?)
+    Ok(client.get_multiplexed_tokio_connection().await?)
 }
 
 /// Defensive helpers to parse redis::Value
@@ -352,74 +873,63 @@ fn as_data(v: &redis::Value) -> Option<&[u8]> {
     }
 }
 
-/// Persisted string getter (None if missing or wrong type)
-fn redis_get_string(con: &mut redis::Connection, key: &str) -> Result<Option<String>> {
-    let v: Option<redis::Value> = con.get(key).ok();
-    match v {
-        Some(redis::Value::BulkString(b)) => Ok(Some(try_string_from_bytes(&b))),
-        Some(redis::Value::SimpleString(s)) => Ok(Some(s.clone())),
-        Some(redis::Value::Okay) => Ok(Some("OK".to_string())),
-        _ => Ok(None),
-    }
-}
+/// Materialize a job's `payload` field to a local file the Python runner can
+/// read, without ever holding the whole payload twice in memory. Inline
+/// payloads (already bounded by `MAX_INLINE_PAYLOAD_BYTES`) are written
+/// directly; referenced payloads are pulled record-by-record through
+/// `streaming::for_each_record`'s reused chunk buffer straight into the file.
+async fn stage_payload(
+    con: &mut redis::aio::MultiplexedConnection,
+    jid: &str,
+    payload: &[u8],
+    max_inline_payload_bytes: usize,
+) -> Result<()> {
+    use std::io::Write;
 
-fn try_string_from_bytes(bytes: &[u8]) -> String {
-    String::from_utf8(bytes.to_vec()).unwrap_or_else(|e| {
-        // lossy fallback to avoid panics on corrupted storage
-        String::from_utf8_lossy(&e.into_bytes()).into_owned()
-    })
-}
+    let path = format!("/tmp/videogen-payload-{jid}.ndjson");
 
-/// Mark a processing checkpoint: we record intent to process before side effects.
-/// This allows the handler to act idempotently on replays.
-/// Data model choices (simple & explicit):
-///  - Hash: videogen:processing:<entry_id> → { jid, ts_ms } with TTL for leak prevention
-///  - Key : videogen:completed:<entry_id>  → ts_ms (string) with TTL to cap growth
-fn mark_processing(con: &mut redis::Connection, entry_id: &str, jid: &str) -> Result<()> {
-    let key = format!("{PROCESSING_KEY_NS}:{entry_id}");
-    let ts_ms = now_ms();
-    let _: () = con.hset(&key, "jid", jid)?;
-    let _: () = con.hset(&key, "ts_ms", ts_ms)?;
-    let _: bool = con.expire(&key, PROCESSING_TTL_SECS)?;
-    Ok(())
-}
-
-fn is_completed(con: &mut redis::Connection, entry_id: &str, _jid: &str) -> Result<bool> {
-    // For multi-tenant you could key per-stream/tenant; we keep it simple.
-    let key = format!("{COMPLETED_KEY_NS}:{entry_id}");
-    if con.exists(&key)? {
-        return Ok(true);
+    match streaming::classify(payload) {
+        Payload::Inline(bytes) => {
+            if bytes.len() > max_inline_payload_bytes {
+                bail!(
+                    "inline payload for jid={jid} is {} bytes, over the {} byte budget",
+                    bytes.len(),
+                    max_inline_payload_bytes
+                );
+            }
+            fs::write(&path, bytes).context("failed to write inline payload")?;
+        }
+        Payload::Ref(redis_key) => {
+            let mut file = fs::File::create(&path).context("failed to create payload file")?;
+            streaming::for_each_record(con, redis_key, |record| {
+                file.write_all(record)?;
+                file.write_all(b"\n")?;
+                Ok(())
+            })
+            .await?;
+        }
     }
-    // Backward compatibility for legacy Set-based markers.
-    con.sismember(COMPLETED_KEY_NS, entry_id)
-        .map_err(Into::into)
-}
 
-fn mark_completed(con: &mut redis::Connection, entry_id: &str, _jid: &str) -> Result<()> {
-    let key = format!("{COMPLETED_KEY_NS}:{entry_id}");
-    let ts_ms = now_ms();
-    con.set_ex::<_, _, ()>(&key, ts_ms, COMPLETED_TTL_SECS)?;
-
-    // Best-effort cleanup of the processing checkpoint now that we are done.
-    let processing_key = format!("{PROCESSING_KEY_NS}:{entry_id}");
-    if let Err(e) = redis::cmd("DEL")
-        .arg(&processing_key)
-        .query::<()>(&mut *con)
-    {
-        eprintln!("[processing.cleanup.error] entry_id={entry_id} err={e}");
-    }
+    con.hset::<_, _, _, ()>(format!("job:{jid}"), "payload_path", &path)
+        .await
+        .context("failed to record payload_path")?;
     Ok(())
 }
 
-/// Run the external Python job runner with a soft timeout.
-/// NOTE: std::process has no built-in timeout; in production:
-///   - Use the `wait_timeout` crate or run under Tokio and `tokio::time::timeout`.
-///   - If timeout elapses, kill the child and return an error.
-///   - Add retries with **exponential backoff + jitter** (TODO circuit-breaker integration).
-fn run_python_for(con: &mut redis::Connection, jid: &str, timeout_s: u64) -> Result<()> {
+/// Run the external Python job runner under a real `tokio::time::timeout`,
+/// racing it against the job lock being lost so a losing worker kills its
+/// subprocess instead of letting it keep writing side effects we no longer
+/// own. On either timeout or lock loss the child is killed and reaped
+/// cleanly (no zombie left behind).
+async fn run_python_for(
+    con: &mut redis::aio::MultiplexedConnection,
+    jid: &str,
+    timeout_s: u64,
+    job_lock: Option<&JobLock>,
+) -> Result<()> {
     // Idempotency hint: If the job already has a stable result (e.g., result_url),
     // short-circuit to success to avoid duplicate side effects.
-    if let Ok(Some(url)) = get_nonempty_hget(con, &format!("job:{jid}"), "result_url") {
+    if let Ok(Some(url)) = get_nonempty_hget(con, &format!("job:{jid}"), "result_url").await {
         eprintln!("[handler.idempotent.shortcut] jid={jid} url={url}");
         return Ok(());
     }
@@ -433,34 +943,44 @@ fn run_python_for(con: &mut redis::Connection, jid: &str, timeout_s: u64) -> Res
         .spawn()
         .context("failed to spawn python runner")?;
 
-    // Pseudo-timeout (best-effort) — replace with wait_timeout or tokio in production.
-    let start = std::time::Instant::now();
-    loop {
-        match child.try_wait() {
-            Ok(Some(status)) => {
-                if !status.success() {
-                    bail!("python runner failed with status {:?}", status.code());
-                }
-                break;
+    let lock_lost = async {
+        loop {
+            if job_lock.is_some_and(|l| l.lost()) {
+                return;
             }
-            Ok(None) => {
-                if start.elapsed() >= Duration::from_secs(timeout_s) {
-                    // Kill and error
-                    let _ = child.kill();
-                    bail!("python runner timeout after {}s", timeout_s);
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    };
+
+    tokio::select! {
+        outcome = tokio::time::timeout(Duration::from_secs(timeout_s), child.wait()) => {
+            match outcome {
+                Ok(Ok(status)) => {
+                    if !status.success() {
+                        bail!("python runner failed with status {:?}", status.code());
+                    }
+                }
+                Ok(Err(e)) => {
+                    bail!("python runner wait error: {e}");
+                }
+                Err(_) => {
+                    let _ = child.kill().await;
+                    let _ = child.wait().await;
+                    bail!("python runner timeout after {timeout_s}s");
                 }
-                thread::sleep(Duration::from_millis(100));
-            }
-            Err(e) => {
-                let _ = child.kill();
-                bail!("python runner wait error: {e}");
             }
         }
+        _ = lock_lost => {
+            let _ = child.kill().await;
+            let _ = child.wait().await;
+            bail!("lost job lock for jid={jid} mid-run");
+        }
     }
 
     // Ensure the runner wrote a result
     let url: String = con
         .hget(format!("job:{jid}"), "result_url")
+        .await
         .unwrap_or_default();
     if url.is_empty() {
         bail!("no result_url set by runner");
@@ -468,12 +988,12 @@ fn run_python_for(con: &mut redis::Connection, jid: &str, timeout_s: u64) -> Res
     Ok(())
 }
 
-fn get_nonempty_hget(
-    con: &mut redis::Connection,
+async fn get_nonempty_hget(
+    con: &mut redis::aio::MultiplexedConnection,
     key: &str,
     field: &str,
 ) -> Result<Option<String>> {
-    let v: Option<String> = con.hget(key, field).ok();
+    let v: Option<String> = con.hget(key, field).await.ok();
     Ok(v.filter(|s| !s.is_empty()))
 }
 
@@ -481,8 +1001,8 @@ fn get_nonempty_hget(
 /// Uses current time minus `trim_minutes`. MINID is a **safer** policy than MAXLEN for time-based retention:
 /// it preserves recent items regardless of burst size. Use MAXLEN (approx) when you care only about memory bounds.
 /// For services with SLAs tied to “redelivery window” and audits, time-based MINID is more predictable.
-fn trim_stream_minid(
-    con: &mut redis::Connection,
+async fn trim_stream_minid(
+    con: &mut redis::aio::MultiplexedConnection,
     stream: &str,
     last_id: &str,
     trim_minutes: u64,
@@ -521,7 +1041,8 @@ fn trim_stream_minid(
         .arg("MINID")
         .arg("~")
         .arg(&effective_minid)
-        .query(con)?;
+        .query_async(con)
+        .await?;
     Ok(())
 }
 
@@ -0,0 +1,151 @@
+//! Fixed-capacity in-process cache of recently observed completion/processing
+//! markers, consulted by `process_entry` before `is_completed` hits Redis.
+//!
+//! Hot replays and catch-up-from-`0-0` runs re-check idempotency markers for
+//! entries this very process already saw, so a positive "completed" result
+//! this process itself produced (via `mark_completed`) is cached and can
+//! short-circuit the `EXISTS`/`SISMEMBER` round-trip entirely. Everything
+//! else — misses, and anything not positively known as completed — still
+//! falls through to Redis, which stays the source of truth across workers.
+
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarkerState {
+    Processing,
+    Completed,
+}
+
+/// Capacity-bounded map of `entry_id` -> last observed marker state, evicting
+/// the least-recently-used entry once `capacity` is exceeded. `order` tracks
+/// recency (front = least recently used, back = most recently used); both
+/// reads and writes touch an entry's position, so a frequently-replayed
+/// `entry_id` stays resident instead of aging out on insertion order alone —
+/// the case that matters most for the hot-replay workload this cache exists
+/// for.
+pub struct MarkerCache {
+    capacity: usize,
+    states: HashMap<String, MarkerState>,
+    order: VecDeque<String>,
+}
+
+impl MarkerCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            states: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// True only if this process has itself observed `entry_id` as
+    /// completed. Callers must treat anything else (miss, or a cached
+    /// `Processing` state) as "unknown" and verify against Redis — this
+    /// cache is only ever authoritative in the positive direction.
+    pub fn is_completed(&mut self, entry_id: &str) -> bool {
+        let completed = matches!(self.states.get(entry_id), Some(MarkerState::Completed));
+        if completed {
+            self.touch(entry_id);
+        }
+        completed
+    }
+
+    /// Record that `entry_id` completed, so a later redelivery in this same
+    /// process can short-circuit without a network call.
+    pub fn mark_completed(&mut self, entry_id: &str) {
+        self.insert(entry_id, MarkerState::Completed);
+    }
+
+    /// Record that `entry_id` is now checkpointed as processing. Never
+    /// downgrades an entry already known completed.
+    pub fn mark_processing(&mut self, entry_id: &str) {
+        if !self.is_completed(entry_id) {
+            self.insert(entry_id, MarkerState::Processing);
+        }
+    }
+
+    /// Move an already-resident `entry_id` to the most-recently-used end.
+    fn touch(&mut self, entry_id: &str) {
+        if let Some(pos) = self.order.iter().position(|id| id == entry_id) {
+            if let Some(id) = self.order.remove(pos) {
+                self.order.push_back(id);
+            }
+        }
+    }
+
+    fn insert(&mut self, entry_id: &str, state: MarkerState) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.states.insert(entry_id.to_string(), state).is_none() {
+            self.order.push_back(entry_id.to_string());
+            if self.order.len() > self.capacity {
+                if let Some(evicted) = self.order.pop_front() {
+                    self.states.remove(&evicted);
+                }
+            }
+        } else {
+            self.touch(entry_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_the_least_recently_used_entry_once_over_capacity() {
+        let mut cache = MarkerCache::new(2);
+        cache.mark_completed("1-1");
+        cache.mark_completed("1-2");
+        cache.mark_completed("1-3"); // over capacity: 1-1 is least recently touched
+
+        assert!(!cache.is_completed("1-1"), "1-1 should have been evicted");
+        assert!(cache.is_completed("1-2"));
+        assert!(cache.is_completed("1-3"));
+    }
+
+    #[test]
+    fn a_read_hit_promotes_an_entry_so_it_outlives_a_never_touched_one() {
+        let mut cache = MarkerCache::new(2);
+        cache.mark_completed("1-1");
+        cache.mark_completed("1-2");
+
+        // Touching 1-1 again moves it to most-recently-used, ahead of 1-2.
+        assert!(cache.is_completed("1-1"));
+
+        cache.mark_completed("1-3"); // over capacity: 1-2 is now least recently used
+
+        assert!(cache.is_completed("1-1"), "1-1 was promoted by the read and should survive");
+        assert!(!cache.is_completed("1-2"), "1-2 should have been evicted");
+        assert!(cache.is_completed("1-3"));
+    }
+
+    #[test]
+    fn is_completed_is_false_for_a_cached_processing_marker() {
+        let mut cache = MarkerCache::new(4);
+        cache.mark_processing("1-1");
+
+        // Only a positive "completed" observation is ever authoritative;
+        // "processing" must fall through to Redis rather than short-circuit.
+        assert!(!cache.is_completed("1-1"));
+    }
+
+    #[test]
+    fn mark_processing_never_downgrades_an_already_completed_entry() {
+        let mut cache = MarkerCache::new(4);
+        cache.mark_completed("1-1");
+        cache.mark_processing("1-1");
+
+        assert!(cache.is_completed("1-1"));
+    }
+
+    #[test]
+    fn zero_capacity_cache_never_caches_anything() {
+        let mut cache = MarkerCache::new(0);
+        cache.mark_completed("1-1");
+
+        assert!(!cache.is_completed("1-1"));
+    }
+}
@@ -0,0 +1,108 @@
+//! Bounded-memory streaming of large job payloads.
+//!
+//! `XREAD COUNT` only bounds how many *entries* we pull per call; a single
+//! oversized `payload` field would still get materialized whole into a
+//! `redis::Value` by the client. Rather than widen the existing
+//! `as_bulk`/`as_data` all-at-once parsing to cope with that, large payloads
+//! are kept out of the stream entry entirely: once a payload would exceed
+//! `MAX_INLINE_PAYLOAD_BYTES`, the entry carries only a pointer (a Redis
+//! key), and the bulk content is pulled separately via `GETRANGE` in
+//! fixed-size chunks, reusing one `CHUNK_BYTES` buffer instead of growing a
+//! fresh `Vec` per entry. This keeps per-entry memory bounded and constant
+//! regardless of how large a video-generation input actually is.
+
+use anyhow::{bail, Result};
+use redis::AsyncCommands;
+
+/// Size of the reused read buffer. Small enough to bound memory, large
+/// enough to avoid a GETRANGE round-trip per byte.
+pub const CHUNK_BYTES: usize = 8 * 1024;
+
+/// Safety cap on how much a single referenced payload may stream in, so a
+/// misbehaving producer cannot force unbounded reads.
+const MAX_STREAMED_PAYLOAD_BYTES: usize = 256 * 1024 * 1024;
+
+/// Pointer prefix stored in a stream entry's `payload` field once the real
+/// content was too large to inline: `ref:<redis-key>`.
+const PAYLOAD_REF_PREFIX: &str = "ref:";
+
+/// A decoded `payload` field is either small enough to use as-is, or a
+/// pointer to bulk content stored under a separate key.
+pub enum Payload<'a> {
+    Inline(&'a [u8]),
+    Ref(&'a str),
+}
+
+/// Classify a raw `payload` field extracted from a stream entry.
+pub fn classify(payload_field: &[u8]) -> Payload<'_> {
+    match std::str::from_utf8(payload_field)
+        .ok()
+        .and_then(|s| s.strip_prefix(PAYLOAD_REF_PREFIX))
+    {
+        Some(key) => Payload::Ref(key),
+        None => Payload::Inline(payload_field),
+    }
+}
+
+/// Stream the bulk content stored at `redis_key` through `on_record`,
+/// record-by-record (records are newline-delimited), using one reused
+/// `CHUNK_BYTES` buffer. A trailing partial record at the end of a chunk is
+/// copied to the front of the buffer and completed by the next read rather
+/// than allocating a growing `Vec` per entry.
+pub async fn for_each_record(
+    con: &mut redis::aio::MultiplexedConnection,
+    redis_key: &str,
+    mut on_record: impl FnMut(&[u8]) -> Result<()>,
+) -> Result<()> {
+    let mut buf = [0u8; CHUNK_BYTES];
+    let mut filled: usize = 0; // bytes at the front of `buf` holding a pending partial record
+    let mut cursor: usize = 0; // next GETRANGE start offset into the Redis value
+    let mut total_read: usize = 0;
+
+    loop {
+        let want = buf.len() - filled;
+        if want == 0 {
+            bail!("payload record at {redis_key} exceeds the {CHUNK_BYTES}-byte chunk buffer");
+        }
+        let end = cursor + want - 1;
+        let chunk: Vec<u8> = con.getrange(redis_key, cursor as isize, end as isize).await?;
+        if chunk.is_empty() {
+            break; // no more data at this key
+        }
+
+        cursor += chunk.len();
+        total_read += chunk.len();
+        if total_read > MAX_STREAMED_PAYLOAD_BYTES {
+            bail!(
+                "payload at {redis_key} exceeded the {MAX_STREAMED_PAYLOAD_BYTES}-byte streaming cap"
+            );
+        }
+
+        let short_read = chunk.len() < want;
+        buf[filled..filled + chunk.len()].copy_from_slice(&chunk);
+        let occupied = filled + chunk.len();
+
+        // Emit every complete (newline-terminated) record in the occupied slice.
+        let mut start = 0;
+        while let Some(nl) = buf[start..occupied].iter().position(|&b| b == b'\n') {
+            let record_end = start + nl;
+            on_record(&buf[start..record_end])?;
+            start = record_end + 1;
+        }
+
+        // Carry the trailing partial record to the front of the buffer for the next read.
+        let leftover = occupied - start;
+        buf.copy_within(start..occupied, 0);
+        filled = leftover;
+
+        if short_read {
+            break; // reached the end of the Redis value
+        }
+    }
+
+    if filled > 0 {
+        on_record(&buf[..filled])?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,160 @@
+//! Bounded worker pool that decouples XREAD from job execution.
+//!
+//! The reader task keeps issuing `XREAD BLOCK ... COUNT` and pushes decoded
+//! `(entry_id, jid)` pairs into a bounded `flume` channel; a pool of worker
+//! tasks pulls from that channel and runs jobs. The channel bound *is*
+//! the backpressure knob: once it is full, `send` blocks and the reader
+//! stops issuing XREAD, which caps how much can be buffered in memory
+//! instead of letting one slow GPU job stall reads indefinitely.
+//!
+//! Because workers run concurrently, completions arrive out of order. We
+//! only want to persist `last_id` up to the point where nothing older is
+//! still outstanding, so `CompletionTracker` records entries in read order
+//! and reports the highest contiguous completed prefix.
+
+use std::collections::{HashSet, VecDeque};
+
+/// If the outstanding (submitted-but-not-advanced) prefix grows past this
+/// many entries, log a warning every `STUCK_QUEUE_WARN_STRIDE` entries past
+/// it. `main::process_entry` callers requeue transient contention (lock
+/// busy, a Redis error) up to a bounded number of attempts and give up
+/// (calling `mark_done` anyway) past that, so this should only fire under
+/// a genuine, sustained backlog rather than growing forever — it stays as
+/// the operator-visible signal that it's happening.
+const STUCK_QUEUE_WARN_THRESHOLD: usize = 1000;
+const STUCK_QUEUE_WARN_STRIDE: usize = 1000;
+
+/// One decoded stream entry, ready for a worker to process.
+///
+/// `payload` is the raw `payload` field, if present — always small: either
+/// inline content of at most `MAX_INLINE_PAYLOAD_BYTES`, or a `ref:<key>` pointer
+/// to bulk content fetched separately (see the `streaming` module).
+#[derive(Clone, Debug)]
+pub struct WorkItem {
+    pub entry_id: String,
+    pub jid: String,
+    pub payload: Vec<u8>,
+    /// How many times this exact item has already been requeued after
+    /// transient contention (`ProcessOutcome::Retry` in `main`). Zero for
+    /// every entry the reader submits for the first time.
+    pub requeue_count: u32,
+}
+
+/// Tracks, in read order, which entries have finished processing well
+/// enough to advance past, and reports the highest contiguous prefix so
+/// `last_id` is never persisted past an entry that is still outstanding.
+pub struct CompletionTracker {
+    order: VecDeque<String>,
+    done: HashSet<String>,
+}
+
+impl CompletionTracker {
+    pub fn new() -> Self {
+        Self {
+            order: VecDeque::new(),
+            done: HashSet::new(),
+        }
+    }
+
+    /// Record that `entry_id` was read off the stream and handed to a
+    /// worker, in the order the reader saw it.
+    pub fn submit(&mut self, entry_id: String) {
+        self.order.push_back(entry_id);
+        if self.order.len() >= STUCK_QUEUE_WARN_THRESHOLD
+            && self.order.len().is_multiple_of(STUCK_QUEUE_WARN_STRIDE)
+        {
+            eprintln!(
+                "[completion_tracker.outstanding_backlog] outstanding={} oldest={:?}",
+                self.order.len(),
+                self.order.front()
+            );
+        }
+    }
+
+    /// Record that `entry_id` finished well enough to advance past (success,
+    /// already-completed, permanently failed, malformed-and-skippable,
+    /// evicted by `drop_oldest`, or abandoned after its requeue budget).
+    /// Entries still being requeued after transient contention (lock busy,
+    /// a Redis error, a lock lost mid-run) don't call this until then — they
+    /// stay outstanding and pin the contiguous prefix at that point.
+    pub fn mark_done(&mut self, entry_id: &str) {
+        self.done.insert(entry_id.to_string());
+    }
+
+    /// Pop the highest contiguous run of completed entries off the front of
+    /// read order and return the last one popped, if any advanced.
+    pub fn advance(&mut self) -> Option<String> {
+        let mut last = None;
+        while let Some(front) = self.order.front() {
+            if self.done.remove(front) {
+                last = self.order.pop_front();
+            } else {
+                break;
+            }
+        }
+        last
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advance_is_none_until_the_oldest_entry_is_done() {
+        let mut tracker = CompletionTracker::new();
+        tracker.submit("1-1".to_string());
+        tracker.submit("1-2".to_string());
+
+        tracker.mark_done("1-2");
+        assert_eq!(tracker.advance(), None, "1-1 is still outstanding, so nothing can advance past it");
+
+        tracker.mark_done("1-1");
+        assert_eq!(tracker.advance(), Some("1-2".to_string()), "both done now, advances past the contiguous run");
+    }
+
+    #[test]
+    fn a_stuck_entry_does_not_block_later_mark_done_calls() {
+        let mut tracker = CompletionTracker::new();
+        tracker.submit("1-1".to_string());
+        tracker.submit("1-2".to_string());
+        tracker.submit("1-3".to_string());
+
+        // 1-1 hasn't called mark_done yet (e.g. it is still being requeued
+        // after lock contention in `main::process_entry`) — advance must stay
+        // pinned there, but later entries can still be recorded as done.
+        tracker.mark_done("1-2");
+        tracker.mark_done("1-3");
+        assert_eq!(tracker.advance(), None);
+
+        // Once 1-1 is finally resolved (success, or an explicit give-up),
+        // the whole contiguous run releases at once.
+        tracker.mark_done("1-1");
+        assert_eq!(tracker.advance(), Some("1-3".to_string()));
+    }
+
+    #[test]
+    fn advance_is_idempotent_once_drained() {
+        let mut tracker = CompletionTracker::new();
+        tracker.submit("1-1".to_string());
+        tracker.mark_done("1-1");
+
+        assert_eq!(tracker.advance(), Some("1-1".to_string()));
+        assert_eq!(tracker.advance(), None, "nothing left outstanding to advance past");
+    }
+
+    #[test]
+    fn mark_done_for_an_entry_never_submitted_is_a_harmless_no_op() {
+        let mut tracker = CompletionTracker::new();
+        tracker.submit("1-1".to_string());
+
+        // A redelivery or a race could report completion for an entry this
+        // tracker instance never saw submitted; it must not panic or affect
+        // 1-1's own advancement.
+        tracker.mark_done("9-9");
+        assert_eq!(tracker.advance(), None);
+
+        tracker.mark_done("1-1");
+        assert_eq!(tracker.advance(), Some("1-1".to_string()));
+    }
+}